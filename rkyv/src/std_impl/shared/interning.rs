@@ -0,0 +1,205 @@
+//! An opt-in [`SharedSerializer`] decorator that interns shared pointees by the content hash of
+//! their serialized bytes rather than by allocation address.
+
+use std::collections::{hash_map::DefaultHasher, HashMap};
+use std::hash::{Hash, Hasher};
+
+use crate::{ser::SharedSerializer, SerializeUnsized};
+
+/// Wraps a [`SharedSerializer`] to deduplicate shared pointees by the content hash of their
+/// serialized bytes, collapsing equal-but-distinct allocations (e.g. two independently-built
+/// `Rc<str>`s holding the same text) into a single archived copy.
+///
+/// Interning is opt-in and off by default (see [`new`](Self::new) vs.
+/// [`with_interning`](Self::with_interning)). With interning off, every pointee passed to
+/// `serialize_shared` is written out again through this wrapper; the wrapped serializer's own
+/// `serialize_shared` is not called, so pointees are not deduplicated by address either.
+///
+/// Only sound for pointees whose serialized bytes are self-contained — a flat `str`/`[u8]` blob,
+/// not a type that resolves a `RelPtr` against its own position, since a value is serialized into
+/// a scratch buffer first (so its bytes can be hashed before anything is committed to the
+/// archive) and `pos()` reports a position within that scratch buffer, not its eventual home.
+pub struct InterningSerializer<S> {
+    inner: S,
+    enabled: bool,
+    by_hash: HashMap<u64, usize>,
+    capture: Option<Vec<u8>>,
+}
+
+impl<S> InterningSerializer<S> {
+    /// Wraps `inner` with interning off; `serialize_shared` serializes each pointee it is given
+    /// in full, without deduplicating by content or by address.
+    #[inline]
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            enabled: false,
+            by_hash: HashMap::new(),
+            capture: None,
+        }
+    }
+
+    /// Wraps `inner` with interning on.
+    #[inline]
+    pub fn with_interning(inner: S) -> Self {
+        Self {
+            inner,
+            enabled: true,
+            by_hash: HashMap::new(),
+            capture: None,
+        }
+    }
+
+    /// Unwraps this decorator, discarding the content-hash table.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: SharedSerializer> SharedSerializer for InterningSerializer<S> {
+    type Error = S::Error;
+
+    #[inline]
+    fn pos(&self) -> usize {
+        match &self.capture {
+            Some(capture) => capture.len(),
+            None => self.inner.pos(),
+        }
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        match &mut self.capture {
+            Some(capture) => {
+                capture.extend_from_slice(bytes);
+                Ok(())
+            }
+            None => self.inner.write(bytes),
+        }
+    }
+
+    fn serialize_shared<T: SerializeUnsized<Self> + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<usize, Self::Error> {
+        if !self.enabled {
+            return value.serialize_unsized(self);
+        }
+
+        // Serialize into a scratch buffer so the bytes can be hashed before anything is
+        // committed to the archive; this is what makes interning actually save space, rather than
+        // just writing the duplicate anyway and pointing elsewhere at it.
+        let outer_capture = self.capture.replace(Vec::new());
+        value.serialize_unsized(self)?;
+        let scratch = self.capture.take().expect("capture was just set above");
+        self.capture = outer_capture;
+
+        let mut hasher = DefaultHasher::new();
+        scratch.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        if let Some(&pos) = self.by_hash.get(&hash) {
+            return Ok(pos);
+        }
+
+        let pos = self.inner.pos();
+        self.inner.write(&scratch)?;
+        self.by_hash.insert(hash, pos);
+        Ok(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use super::*;
+
+    /// A bare-bones [`SharedSerializer`] that just appends to an in-memory buffer, with no dedup
+    /// of its own, so these tests only exercise `InterningSerializer`'s own behavior.
+    struct FlatSerializer {
+        bytes: Vec<u8>,
+    }
+
+    impl SharedSerializer for FlatSerializer {
+        type Error = Infallible;
+
+        fn pos(&self) -> usize {
+            self.bytes.len()
+        }
+
+        fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+            self.bytes.extend_from_slice(bytes);
+            Ok(())
+        }
+
+        fn serialize_shared<T: SerializeUnsized<Self> + ?Sized>(
+            &mut self,
+            value: &T,
+        ) -> Result<usize, Self::Error> {
+            value.serialize_unsized(self)
+        }
+    }
+
+    struct Blob<'a>(&'a [u8]);
+
+    impl crate::ArchiveUnsized for Blob<'_> {
+        type Archived = [u8];
+        type MetadataResolver = ();
+
+        fn resolve_unsized(
+            &self,
+            pos: usize,
+            target_pos: usize,
+            _resolver: (),
+            out: &mut core::mem::MaybeUninit<crate::RelPtr<Self::Archived>>,
+        ) {
+            crate::RelPtr::resolve(pos, target_pos, out);
+        }
+    }
+
+    impl<S: SharedSerializer + ?Sized> SerializeUnsized<S> for Blob<'_> {
+        fn serialize_unsized(&self, serializer: &mut S) -> Result<usize, S::Error> {
+            let pos = serializer.pos();
+            serializer.write(self.0)?;
+            Ok(pos)
+        }
+
+        fn serialize_metadata(&self, _: &mut S) -> Result<(), S::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn disabled_by_default_writes_every_duplicate() {
+        let mut serializer = InterningSerializer::new(FlatSerializer { bytes: Vec::new() });
+
+        let first = serializer.serialize_shared(&Blob(b"hello")).unwrap();
+        let second = serializer.serialize_shared(&Blob(b"hello")).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(serializer.into_inner().bytes.len(), b"hello".len() * 2);
+    }
+
+    #[test]
+    fn interning_collapses_equal_content_from_distinct_allocations() {
+        let mut serializer =
+            InterningSerializer::with_interning(FlatSerializer { bytes: Vec::new() });
+
+        let a = String::from("hello");
+        let b = String::from("hello");
+        assert_ne!(a.as_ptr(), b.as_ptr());
+
+        let first = serializer.serialize_shared(&Blob(a.as_bytes())).unwrap();
+        let second = serializer.serialize_shared(&Blob(b.as_bytes())).unwrap();
+        let third = serializer.serialize_shared(&Blob(b"different")).unwrap();
+
+        assert_eq!(first, second);
+        assert_ne!(first, third);
+        assert_eq!(
+            serializer.into_inner().bytes.len(),
+            b"hello".len() + b"different".len()
+        );
+    }
+}
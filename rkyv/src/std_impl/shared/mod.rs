@@ -1,5 +1,7 @@
 //! [`Archive`] implementation for shared pointers.
 
+pub mod interning;
+pub mod registry;
 #[cfg(feature = "validation")]
 pub mod validation;
 
@@ -48,6 +50,34 @@ impl<T: ArchivePointee + ?Sized> ArchivedRc<T> {
     pub unsafe fn get_pin_unchecked(self: Pin<&mut Self>) -> Pin<&mut T> {
         self.map_unchecked_mut(|s| &mut *s.0.as_mut_ptr())
     }
+
+    /// Returns `true` if the two `ArchivedRc`s point to the same allocation.
+    ///
+    /// This compares only the resolved target address, not any pointer metadata, so it reflects
+    /// the original sharing topology of the deserialized graph regardless of `T`'s shape.
+    #[inline]
+    pub fn ptr_eq<U: ArchivePointee + ?Sized>(&self, other: &ArchivedRc<U>) -> bool {
+        self.0.as_ptr() as *const u8 == other.0.as_ptr() as *const u8
+    }
+
+    /// Gets mutable access to the value behind this archived `Rc` if it is uniquely owned.
+    ///
+    /// `counts` must be a reference-count-by-target map built by validating the enclosing
+    /// archive (see [`validation::SharedPointerCountMap`]); this is what lets `get_mut` tell a
+    /// uniquely-owned target apart from one with other live `ArchivedRc`s pointing at it,
+    /// without the blanket `unsafe` of [`get_pin_unchecked`](Self::get_pin_unchecked).
+    #[cfg(feature = "validation")]
+    pub fn get_mut<'a>(
+        this: Pin<&'a mut Self>,
+        counts: &impl validation::SharedPointerCounts,
+    ) -> Option<Pin<&'a mut T>> {
+        let target = this.0.as_ptr() as *const u8 as *const ();
+        if counts.shared_count(target) == 1 {
+            Some(unsafe { this.get_pin_unchecked() })
+        } else {
+            None
+        }
+    }
 }
 
 impl<T: ArchivePointee + ?Sized> Deref for ArchivedRc<T> {
@@ -208,10 +238,11 @@ impl<T: SerializeUnsized<S> + ?Sized + 'static, S: SharedSerializer + ?Sized> Se
     }
 }
 
-// Deserialize can only be implemented for sized types because weak pointers don't have from/into
-// raw functions.
-impl<T: Archive + 'static, D: SharedDeserializer + ?Sized> Deserialize<rc::Weak<T>, D>
-    for Archived<rc::Weak<T>>
+// This routes through the same unsized shared-deserialization path as `ArchivedRc::deserialize`,
+// then downgrades the resulting `Rc<T>`, so it works for unsized `T` (e.g. slices and `str`) as
+// well as sized ones.
+impl<T: ArchiveUnsized + ?Sized + 'static, D: SharedDeserializer + ?Sized>
+    Deserialize<rc::Weak<T>, D> for Archived<rc::Weak<T>>
 where
     T::Archived: DeserializeUnsized<T, D>,
 {
@@ -219,7 +250,15 @@ where
     fn deserialize(&self, deserializer: &mut D) -> Result<rc::Weak<T>, D::Error> {
         Ok(match self {
             ArchivedRcWeak::None => rc::Weak::new(),
-            ArchivedRcWeak::Some(r) => rc::Rc::downgrade(&r.deserialize(deserializer)?),
+            ArchivedRcWeak::Some(r) => {
+                let raw_shared_ptr = deserializer
+                    .deserialize_shared::<T, rc::Rc<T>, _>(r.deref(), |ptr| {
+                        rc::Rc::<T>::from(unsafe { Box::from_raw(ptr) })
+                    })?;
+                let shared_ptr = unsafe { rc::Rc::<T>::from_raw(raw_shared_ptr) };
+                forget(shared_ptr.clone());
+                rc::Rc::downgrade(&shared_ptr)
+            }
         })
     }
 }
@@ -254,6 +293,34 @@ impl<T: ArchivePointee + ?Sized> ArchivedArc<T> {
     pub unsafe fn get_pin_unchecked(self: Pin<&mut Self>) -> Pin<&mut T> {
         self.map_unchecked_mut(|s| &mut *s.0.as_mut_ptr())
     }
+
+    /// Returns `true` if the two `ArchivedArc`s point to the same allocation.
+    ///
+    /// This compares only the resolved target address, not any pointer metadata, so it reflects
+    /// the original sharing topology of the deserialized graph regardless of `T`'s shape.
+    #[inline]
+    pub fn ptr_eq<U: ArchivePointee + ?Sized>(&self, other: &ArchivedArc<U>) -> bool {
+        self.0.as_ptr() as *const u8 == other.0.as_ptr() as *const u8
+    }
+
+    /// Gets mutable access to the value behind this archived `Arc` if it is uniquely owned.
+    ///
+    /// `counts` must be a reference-count-by-target map built by validating the enclosing
+    /// archive (see [`validation::SharedPointerCountMap`]); this is what lets `get_mut` tell a
+    /// uniquely-owned target apart from one with other live `ArchivedArc`s pointing at it,
+    /// without the blanket `unsafe` of [`get_pin_unchecked`](Self::get_pin_unchecked).
+    #[cfg(feature = "validation")]
+    pub fn get_mut<'a>(
+        this: Pin<&'a mut Self>,
+        counts: &impl validation::SharedPointerCounts,
+    ) -> Option<Pin<&'a mut T>> {
+        let target = this.0.as_ptr() as *const u8 as *const ();
+        if counts.shared_count(target) == 1 {
+            Some(unsafe { this.get_pin_unchecked() })
+        } else {
+            None
+        }
+    }
 }
 
 impl<T: ArchivePointee + ?Sized> Deref for ArchivedArc<T> {
@@ -415,10 +482,11 @@ impl<T: SerializeUnsized<S> + ?Sized + 'static, S: SharedSerializer + ?Sized> Se
     }
 }
 
-// Deserialize can only be implemented for sized types because weak pointers don't have from/into
-// raw functions.
-impl<T: Archive + 'static, D: SharedDeserializer + ?Sized> Deserialize<sync::Weak<T>, D>
-    for Archived<sync::Weak<T>>
+// This routes through the same unsized shared-deserialization path as `ArchivedArc::deserialize`,
+// then downgrades the resulting `Arc<T>`, so it works for unsized `T` (e.g. slices and `str`) as
+// well as sized ones.
+impl<T: ArchiveUnsized + ?Sized + 'static, D: SharedDeserializer + ?Sized>
+    Deserialize<sync::Weak<T>, D> for Archived<sync::Weak<T>>
 where
     T::Archived: DeserializeUnsized<T, D>,
 {
@@ -426,7 +494,15 @@ where
     fn deserialize(&self, deserializer: &mut D) -> Result<sync::Weak<T>, D::Error> {
         Ok(match self {
             ArchivedArcWeak::None => sync::Weak::new(),
-            ArchivedArcWeak::Some(r) => sync::Arc::downgrade(&r.deserialize(deserializer)?),
+            ArchivedArcWeak::Some(r) => {
+                let raw_shared_ptr = deserializer
+                    .deserialize_shared::<T, sync::Arc<T>, _>(r.deref(), |ptr| {
+                        sync::Arc::<T>::from(unsafe { Box::from_raw(ptr) })
+                    })?;
+                let shared_ptr = unsafe { sync::Arc::<T>::from_raw(raw_shared_ptr) };
+                forget(shared_ptr.clone());
+                sync::Arc::downgrade(&shared_ptr)
+            }
         })
     }
 }
@@ -0,0 +1,801 @@
+//! Type-erased shared pointers for trait objects (`Rc<dyn Trait>` / `Arc<dyn Trait>`).
+//!
+//! [`ArchivedRc<T>`](super::ArchivedRc) only needs `T: ArchivePointee + ?Sized`, but there's
+//! still no way to get there for a `dyn Trait`: the concrete type behind the vtable is erased at
+//! runtime, so there's nothing for a derive to generate an `Archive` impl for. This module adds
+//! a small registry that maps a stable, per-implementor [`TypeTag`] to the serialize/
+//! deserialize/validate thunks needed to rebuild the correct concrete `Rc<Concrete>` (and then
+//! unsize it back into `Rc<dyn Trait>`) purely from the tag written alongside the pointer.
+//!
+//! `dyn Trait` can't be named generically, so the [`archive_dyn!`] macro generates the
+//! `ArchiveUnsized`/`SerializeUnsized` impls for one concrete trait at a time; see its docs for
+//! what your trait needs to provide.
+
+use core::{
+    any::{Any, TypeId},
+    fmt,
+    marker::PhantomData,
+    mem::{forget, MaybeUninit},
+};
+use std::{boxed::Box, collections::HashMap, error::Error as StdError, rc, sync};
+
+use crate::{
+    de::SharedDeserializer, offset_of, project_struct, ser::SharedSerializer, Archive,
+    ArchivePointee, DeserializeUnsized, RelPtr, SerializeUnsized,
+};
+#[cfg(feature = "validation")]
+use crate::validation::ArchiveContext;
+#[cfg(feature = "validation")]
+use bytecheck::CheckBytes;
+
+/// A stable identifier for a concrete type registered behind some `dyn Trait` shared pointer.
+///
+/// Unlike [`TypeId`], a `TypeTag` is chosen by the caller and stays stable across compilations,
+/// so it's safe to write one into an archive and read it back in a different process or binary.
+pub type TypeTag = u64;
+
+/// Associates a concrete implementor of some `dyn Trait` with the [`TypeTag`] that identifies it
+/// in a [`TypeRegistry`]/[`SerializeRegistry`].
+///
+/// Implement this (a one-line manual impl per concrete type) for every
+/// concrete type that should be archivable behind `Rc<dyn Trait>`/`Arc<dyn Trait>`.
+pub trait DynTypeTag {
+    /// The stable type tag for this concrete type.
+    const TYPE_TAG: TypeTag;
+}
+
+/// Implemented for every concrete type that can serialize itself as the pointee of a
+/// type-erased `Rc<dyn Trait>`/`Arc<dyn Trait>`.
+///
+/// Blanket-implemented for any `T: DynTypeTag + SerializeUnsized<S>`, so a concrete type opts
+/// into trait-object archiving just by implementing [`DynTypeTag`].
+pub trait SerializeDyn<S: SharedSerializer + ?Sized> {
+    /// Serializes `self` as the pointee of a type-erased shared pointer, returning the position
+    /// `serialize_shared` gave it alongside its type tag, ready to write into an
+    /// [`ArchivedDyn`].
+    fn serialize_dyn(&self, serializer: &mut S) -> Result<(usize, TypeTag), S::Error>;
+}
+
+impl<T, S> SerializeDyn<S> for T
+where
+    T: DynTypeTag + SerializeUnsized<S> + 'static,
+    S: SharedSerializer + ?Sized,
+{
+    #[inline]
+    fn serialize_dyn(&self, serializer: &mut S) -> Result<(usize, TypeTag), S::Error> {
+        Ok((serializer.serialize_shared(self)?, T::TYPE_TAG))
+    }
+}
+
+/// The resolver for a type-erased `Rc<dyn Trait>`/`Arc<dyn Trait>` pointee: the position
+/// [`SerializeDyn::serialize_dyn`] archived it at, paired with its [`TypeTag`].
+pub struct DynResolver {
+    pos: usize,
+    tag: TypeTag,
+}
+
+impl DynResolver {
+    /// Builds a resolver from the `(position, tag)` pair returned by
+    /// [`SerializeDyn::serialize_dyn`].
+    #[inline]
+    pub fn new(pos: usize, tag: TypeTag) -> Self {
+        Self { pos, tag }
+    }
+}
+
+/// The archived form of a type-erased `Rc<dyn Trait>`/`Arc<dyn Trait>`.
+///
+/// This is a thin wrapper carrying a [`RelPtr`] to the archived concrete value alongside the
+/// [`TypeTag`] recorded for it at serialize time. `Trait` only appears as a marker: the pointee
+/// is addressed as raw bytes and rebuilt into a concrete type by looking up `tag` in a
+/// [`TypeRegistry`].
+#[repr(C)]
+pub struct ArchivedDyn<Trait: ?Sized> {
+    ptr: RelPtr<()>,
+    tag: TypeTag,
+    _phantom: PhantomData<fn() -> Box<Trait>>,
+}
+
+impl<Trait: ?Sized> ArchivedDyn<Trait> {
+    /// The type tag recorded for the concrete value behind this pointer.
+    #[inline]
+    pub fn type_tag(&self) -> TypeTag {
+        self.tag
+    }
+
+    /// Returns a raw pointer to the archived concrete value's bytes.
+    ///
+    /// This is only meaningful once `tag` has been resolved against a [`TypeRegistry`]; the
+    /// registry entry is what knows how to interpret the bytes it points to.
+    #[inline]
+    pub fn data_ptr(&self) -> *const () {
+        unsafe { self.ptr.as_ptr() }
+    }
+
+    /// Writes this pointer's relative offset and type tag into `out`, completing the resolve
+    /// step for a value serialized via [`SerializeDyn::serialize_dyn`].
+    ///
+    /// Mirrors [`ArchivedRc`](super::ArchivedRc)'s own resolve (reached through `Archive::
+    /// resolve` on `Rc<T>`), except there's no `T::Archived` to delegate to: the pointee's type
+    /// isn't known until `tag` is looked up in a [`TypeRegistry`], so the tag travels alongside
+    /// the pointer as a plain field instead of riding in `RelPtr`'s metadata slot.
+    #[inline]
+    pub fn resolve(pos: usize, resolver: DynResolver, out: &mut MaybeUninit<Self>) {
+        unsafe {
+            RelPtr::resolve(
+                pos + offset_of!(Self, ptr),
+                resolver.pos,
+                project_struct!(out: Self => ptr),
+            );
+            project_struct!(out: Self => tag)
+                .as_mut_ptr()
+                .write(resolver.tag);
+        }
+    }
+}
+
+unsafe impl<Trait: ?Sized> ArchivePointee for ArchivedDyn<Trait> {
+    type ArchivedMetadata = ();
+
+    #[inline]
+    fn pointer_metadata(_: &Self::ArchivedMetadata) -> Self::ArchivedMetadata {}
+}
+
+/// Reconstructs a concrete `Rc<T>`/`Arc<T>` from the raw pointer
+/// [`SharedDeserializer::deserialize_shared`] hands back, abstracting over which shared-pointer
+/// family a [`TypeRegistry`] entry reconstructs into.
+trait FromRawShared<T: ?Sized> {
+    unsafe fn from_raw_shared(ptr: *mut T) -> Self;
+}
+
+impl<T: ?Sized> FromRawShared<T> for rc::Rc<T> {
+    #[inline]
+    unsafe fn from_raw_shared(ptr: *mut T) -> Self {
+        rc::Rc::from_raw(ptr)
+    }
+}
+
+impl<T: ?Sized> FromRawShared<T> for sync::Arc<T> {
+    #[inline]
+    unsafe fn from_raw_shared(ptr: *mut T) -> Self {
+        sync::Arc::from_raw(ptr)
+    }
+}
+
+/// One concrete type's entry in a [`TypeRegistry`]: the thunks needed to deserialize (and,
+/// behind `feature = "validation"`, validate) a value of that type from its archived bytes.
+///
+/// `C` is only meaningful behind `feature = "validation"`; without it, registries don't carry a
+/// validation context and `C` defaults to `()`.
+struct RegisteredType<Strong, D: SharedDeserializer + ?Sized, C: ?Sized> {
+    deserialize: Box<dyn Fn(*const (), &mut D) -> Result<Strong, D::Error>>,
+    #[cfg(feature = "validation")]
+    check_bytes: Box<dyn Fn(*const (), &mut C) -> Result<(), Box<dyn StdError>>>,
+    #[cfg(not(feature = "validation"))]
+    _check_context: PhantomData<fn(&mut C)>,
+}
+
+/// A registry of concrete types that may appear behind a type-erased `Rc<dyn Trait>`/
+/// `Arc<dyn Trait>`, keyed by [`TypeTag`].
+///
+/// `Strong` is the final shared-pointer type `ArchivedDyn::deserialize` reconstructs — typically
+/// `Rc<Trait>` or `Arc<Trait>` for whatever trait this registry's entries implement. `D` is the
+/// deserializer the registry's entries deserialize against; `C` is the validation context its
+/// entries are checked against (only meaningful behind `feature = "validation"`, and otherwise
+/// left as the default `()`).
+pub struct TypeRegistry<Strong, D: SharedDeserializer + ?Sized, C: ?Sized = ()> {
+    entries: HashMap<TypeTag, RegisteredType<Strong, D, C>>,
+}
+
+impl<Strong: 'static, D: SharedDeserializer + ?Sized, C: ?Sized> Default
+    for TypeRegistry<Strong, D, C>
+{
+    #[inline]
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<Strong: 'static, D: SharedDeserializer + ?Sized, C: ?Sized> TypeRegistry<Strong, D, C> {
+    /// Creates an empty registry.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Looks up the entry registered for `tag`, if any.
+    fn get(&self, tag: TypeTag) -> Option<&RegisteredType<Strong, D, C>> {
+        self.entries.get(&tag)
+    }
+}
+
+#[cfg(not(feature = "validation"))]
+impl<Strong: 'static, D: SharedDeserializer + ?Sized, C: ?Sized> TypeRegistry<Strong, D, C> {
+    /// Registers `T` as a concrete implementor that may appear behind a type-erased shared
+    /// pointer, reconstructed as a `P` (e.g. `Rc<T>`/`Arc<T>`) before being unsized into `Strong`
+    /// (e.g. `Rc<Trait>`/`Arc<Trait>`).
+    ///
+    /// `unsize` performs the otherwise-unnameable `P -> Strong` coercion; callers typically pass
+    /// `|p| p` and let unsizing coercion do the work at the call site.
+    pub fn register<T, P>(&mut self, unsize: fn(P) -> Strong)
+    where
+        T: DynTypeTag + Archive + 'static,
+        T::Archived: DeserializeUnsized<T, D>,
+        P: FromRawShared<T> + From<Box<T>> + Clone + 'static,
+    {
+        self.entries.insert(
+            T::TYPE_TAG,
+            RegisteredType {
+                deserialize: deserialize_thunk(unsize),
+                _check_context: PhantomData,
+            },
+        );
+    }
+}
+
+#[cfg(feature = "validation")]
+impl<Strong: 'static, D: SharedDeserializer + ?Sized, C: ?Sized> TypeRegistry<Strong, D, C> {
+    /// Registers `T` as a concrete implementor that may appear behind a type-erased shared
+    /// pointer, reconstructed as a `P` (e.g. `Rc<T>`/`Arc<T>`) before being unsized into `Strong`
+    /// (e.g. `Rc<Trait>`/`Arc<Trait>`).
+    ///
+    /// `unsize` performs the otherwise-unnameable `P -> Strong` coercion; callers typically pass
+    /// `|p| p` and let unsizing coercion do the work at the call site.
+    pub fn register<T, P>(&mut self, unsize: fn(P) -> Strong)
+    where
+        T: DynTypeTag + Archive + 'static,
+        T::Archived: DeserializeUnsized<T, D> + CheckBytes<C>,
+        <T::Archived as CheckBytes<C>>::Error: StdError + 'static,
+        P: FromRawShared<T> + From<Box<T>> + Clone + 'static,
+    {
+        self.entries.insert(
+            T::TYPE_TAG,
+            RegisteredType {
+                deserialize: deserialize_thunk(unsize),
+                check_bytes: Box::new(move |ptr, context| unsafe {
+                    T::Archived::check_bytes(ptr.cast(), context)
+                        .map(|_| ())
+                        .map_err(|e| Box::new(e) as Box<dyn StdError>)
+                }),
+            },
+        );
+    }
+}
+
+/// Builds the deserialize thunk shared by both the validating and non-validating `register`.
+///
+/// Goes through `deserializer.deserialize_shared`, the same entry point `Rc<T>`/`Arc<T>`'s own
+/// `Deserialize` impls use, so a type-erased shared pointer gets the same DAG dedup and
+/// `ptr_eq`-preserving identity as an ordinary one — deserializing the archived bytes directly
+/// instead would silently duplicate any target also reachable through a non-erased pointer.
+fn deserialize_thunk<T, P, Strong, D>(
+    unsize: fn(P) -> Strong,
+) -> Box<dyn Fn(*const (), &mut D) -> Result<Strong, D::Error>>
+where
+    T: Archive + 'static,
+    T::Archived: DeserializeUnsized<T, D>,
+    P: FromRawShared<T> + From<Box<T>> + Clone + 'static,
+    Strong: 'static,
+    D: SharedDeserializer + ?Sized,
+{
+    Box::new(move |ptr, deserializer| {
+        let archived = unsafe { &*ptr.cast::<T::Archived>() };
+        let raw =
+            deserializer.deserialize_shared::<T, P, _>(archived, |raw| {
+                P::from(unsafe { Box::from_raw(raw) })
+            })?;
+        let shared_ptr = unsafe { P::from_raw_shared(raw) };
+        forget(shared_ptr.clone());
+        Ok(unsize(shared_ptr))
+    })
+}
+
+/// Errors that can occur deserializing an [`ArchivedDyn`].
+#[derive(Debug)]
+pub enum DynDeserializeError<E> {
+    /// The recorded type tag has no entry in the registry it was deserialized against.
+    UnknownTag(TypeTag),
+    /// The registered entry's own deserialization failed.
+    Inner(E),
+}
+
+impl<E: fmt::Display> fmt::Display for DynDeserializeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTag(tag) => write!(f, "no type registered for tag {}", tag),
+            Self::Inner(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: StdError + 'static> StdError for DynDeserializeError<E> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::UnknownTag(_) => None,
+            Self::Inner(e) => Some(e),
+        }
+    }
+}
+
+/// Errors that can occur validating an [`ArchivedDyn`]. See [`ArchivedDyn::check_bytes`].
+#[derive(Debug)]
+pub enum DynCheckError<C> {
+    /// The relative pointer itself failed its bounds check against the archive buffer.
+    PointerCheckFailed(C),
+    /// The recorded type tag has no entry in the registry it was checked against.
+    UnknownTag(TypeTag),
+    /// The registered entry's own bytecheck failed.
+    PointeeCheckFailed(Box<dyn StdError>),
+}
+
+impl<C: fmt::Display> fmt::Display for DynCheckError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PointerCheckFailed(e) => write!(f, "type-erased pointer failed bounds check: {}", e),
+            Self::UnknownTag(tag) => write!(f, "no type registered for tag {}", tag),
+            Self::PointeeCheckFailed(e) => write!(f, "type-erased pointee failed validation: {}", e),
+        }
+    }
+}
+
+impl<C: StdError + 'static> StdError for DynCheckError<C> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::PointerCheckFailed(e) => Some(e),
+            Self::UnknownTag(_) => None,
+            Self::PointeeCheckFailed(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl<Trait: ?Sized + 'static> ArchivedDyn<Trait> {
+    /// Deserializes the concrete value behind this type-erased pointer, dispatching through
+    /// `registry` to rebuild the right concrete `Strong` (e.g. `Rc<Trait>`/`Arc<Trait>`) for the
+    /// tag that was recorded at serialize time.
+    ///
+    /// # Safety
+    ///
+    /// The caller must have already validated this `ArchivedDyn` (see
+    /// [`check_bytes`](Self::check_bytes)), or otherwise trust the archive it came from; the
+    /// pointee's bytes are read as the registered type's archived form without re-checking them
+    /// here.
+    pub unsafe fn deserialize<Strong, D, C>(
+        &self,
+        registry: &TypeRegistry<Strong, D, C>,
+        deserializer: &mut D,
+    ) -> Result<Strong, DynDeserializeError<D::Error>>
+    where
+        Strong: 'static,
+        D: SharedDeserializer + ?Sized,
+        C: ?Sized,
+    {
+        let entry = registry
+            .get(self.tag)
+            .ok_or(DynDeserializeError::UnknownTag(self.tag))?;
+        (entry.deserialize)(self.data_ptr(), deserializer).map_err(DynDeserializeError::Inner)
+    }
+
+    /// Validates the concrete value behind this type-erased pointer against `registry`,
+    /// confirming the relative pointer resolves within the archive buffer, that `tag` has a
+    /// registered entry, and that the pointee passes that entry's bytecheck, before anything
+    /// attempts to reconstruct a concrete value (and its vtable) from the bytes.
+    #[cfg(feature = "validation")]
+    pub fn check_bytes<Strong: 'static, D: SharedDeserializer + ?Sized, C: ArchiveContext + ?Sized>(
+        &self,
+        registry: &TypeRegistry<Strong, D, C>,
+        context: &mut C,
+    ) -> Result<(), DynCheckError<C::Error>> {
+        let ptr = context
+            .check_rel_ptr(&self.ptr)
+            .map_err(DynCheckError::PointerCheckFailed)?;
+        let entry = registry
+            .get(self.tag)
+            .ok_or(DynCheckError::UnknownTag(self.tag))?;
+        (entry.check_bytes)(ptr, context).map_err(DynCheckError::PointeeCheckFailed)
+    }
+}
+
+/// Gives a validation context access to the [`TypeRegistry`] it should dispatch through when
+/// validating an `ArchivedDyn<Trait>`, so a [`CheckBytes`] recursion from a derive (e.g. a struct
+/// holding `Rc<dyn Trait>`) can reach [`ArchivedDyn::check_bytes`] without an extra argument that
+/// `CheckBytes::check_bytes`'s fixed signature has no slot for.
+///
+/// `Trait` disambiguates between registries when a single context validates more than one kind of
+/// trait object, mirroring [`ProvidesSerializeRegistry`] on the serialize side.
+#[cfg(feature = "validation")]
+pub trait ProvidesTypeRegistry<Trait: ?Sized>: ArchiveContext {
+    /// The final shared-pointer type this registry's entries deserialize into.
+    type Strong: 'static;
+    /// The deserializer this registry's entries deserialize against.
+    type Deserializer: SharedDeserializer + ?Sized;
+
+    /// Returns the registry to dispatch through for `Trait`.
+    fn type_registry(&self) -> rc::Rc<TypeRegistry<Self::Strong, Self::Deserializer, Self>>;
+}
+
+#[cfg(feature = "validation")]
+unsafe impl<Trait, C> CheckBytes<C> for ArchivedDyn<Trait>
+where
+    Trait: ?Sized + 'static,
+    C: ProvidesTypeRegistry<Trait> + ?Sized,
+{
+    type Error = DynCheckError<C::Error>;
+
+    unsafe fn check_bytes<'a>(value: *const Self, context: &mut C) -> Result<&'a Self, Self::Error> {
+        let this = &*value;
+        let registry = context.type_registry();
+        this.check_bytes(&registry, context)?;
+        Ok(this)
+    }
+}
+
+/// A registry of concrete types that may be serialized behind a type-erased `Rc<dyn Trait>`/
+/// `Arc<dyn Trait>`, the serialize-side counterpart to [`TypeRegistry`].
+///
+/// Deserializing dispatches on the [`TypeTag`] read back out of the archive, but serializing
+/// starts from a live `&dyn Trait` with no tag attached yet, so dispatch here goes through
+/// [`Any::type_id`] and `downcast_ref` against each registered concrete type instead. This is why
+/// [`archive_dyn!`] requires `$Trait: Any` as a supertrait: the `&dyn Trait -> &dyn Any` upcast
+/// this relies on only exists for explicitly declared supertraits.
+pub struct SerializeRegistry<S: SharedSerializer + ?Sized> {
+    entries: HashMap<TypeId, Box<dyn Fn(&dyn Any, &mut S) -> Result<(usize, TypeTag), S::Error>>>,
+}
+
+impl<S: SharedSerializer + ?Sized> Default for SerializeRegistry<S> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<S: SharedSerializer + ?Sized> SerializeRegistry<S> {
+    /// Creates an empty registry.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `T` as a concrete type that may be serialized behind a type-erased shared
+    /// pointer, alongside the tag [`ArchivedDyn::deserialize`] will need to reconstruct it.
+    pub fn register<T>(&mut self)
+    where
+        T: DynTypeTag + SerializeDyn<S> + 'static,
+    {
+        self.entries.insert(
+            TypeId::of::<T>(),
+            Box::new(|value, serializer| {
+                let value = value
+                    .downcast_ref::<T>()
+                    .expect("SerializeRegistry entries are keyed by their own TypeId");
+                value.serialize_dyn(serializer)
+            }),
+        );
+    }
+
+    /// Looks up and calls the entry registered for `value`'s concrete type, if any.
+    fn serialize(
+        &self,
+        value: &dyn Any,
+        serializer: &mut S,
+    ) -> Option<Result<(usize, TypeTag), S::Error>> {
+        self.entries
+            .get(&value.type_id())
+            .map(|thunk| thunk(value, serializer))
+    }
+}
+
+/// Gives a serializer access to the [`SerializeRegistry`] it should dispatch through when
+/// serializing `self` behind a type-erased `Rc<dyn Trait>`/`Arc<dyn Trait>`.
+///
+/// `Trait` disambiguates between registries when a single serializer archives more than one kind
+/// of trait object. Implement this on your `Serializer` type (typically returning a registry
+/// built once up front) for every `Trait` passed to [`archive_dyn!`] that you serialize.
+pub trait ProvidesSerializeRegistry<Trait: ?Sized>: SharedSerializer {
+    /// Returns the registry to dispatch through for `Trait`.
+    fn serialize_registry(&self) -> rc::Rc<SerializeRegistry<Self>>;
+}
+
+/// Serializes `value` (a `dyn Trait` behind one of the type-erased shared pointers
+/// [`archive_dyn!`] wires up) by dispatching through `serializer`'s [`SerializeRegistry`],
+/// writing the resulting [`ArchivedDyn`] into the archive and returning its position.
+///
+/// This is the shared implementation behind every `archive_dyn!`-generated `SerializeUnsized`
+/// impl; it isn't meant to be called directly.
+pub fn serialize_dyn_unsized<Trait, S>(
+    value: &Trait,
+    serializer: &mut S,
+) -> Result<usize, S::Error>
+where
+    Trait: AsDynAny + ?Sized,
+    S: ProvidesSerializeRegistry<Trait> + ?Sized,
+{
+    let registry = serializer.serialize_registry();
+    let (concrete_pos, tag) = registry
+        .serialize(value.as_dyn_any(), serializer)
+        .expect("no SerializeRegistry entry for this concrete type")?;
+
+    let pos = serializer.pos();
+    let mut out = MaybeUninit::<ArchivedDyn<Trait>>::uninit();
+    ArchivedDyn::resolve(pos, DynResolver::new(concrete_pos, tag), &mut out);
+    unsafe {
+        let bytes = core::slice::from_raw_parts(
+            out.as_ptr().cast::<u8>(),
+            core::mem::size_of::<ArchivedDyn<Trait>>(),
+        );
+        serializer.write(bytes)?;
+    }
+    Ok(pos)
+}
+
+/// Upcasts `&dyn Trait` to `&dyn Any`, needed so [`SerializeRegistry`] can dispatch on the
+/// concrete type's `TypeId`. Generated by [`archive_dyn!`]; not meant to be implemented by hand.
+pub trait AsDynAny {
+    /// Returns `self` as `&dyn Any`.
+    fn as_dyn_any(&self) -> &dyn Any;
+}
+
+/// Wires `dyn $Trait` up to this module's registries so `Rc<dyn $Trait>`/`Arc<dyn $Trait>` can be
+/// archived. `dyn Trait` can't be named generically, so this is generated per trait rather than
+/// once for every trait.
+///
+/// Requires `$Trait: Any` (a real supertrait on your trait definition, not just `'static`): the
+/// `&dyn $Trait -> &dyn Any` upcast [`SerializeRegistry`] relies on only exists for explicitly
+/// declared supertraits. A serializer that archives `dyn $Trait` must also implement
+/// [`ProvidesSerializeRegistry<dyn $Trait>`].
+#[macro_export]
+macro_rules! archive_dyn {
+    ($Trait:path) => {
+        impl $crate::std_impl::shared::registry::AsDynAny for dyn $Trait {
+            #[inline]
+            fn as_dyn_any(&self) -> &dyn core::any::Any {
+                self
+            }
+        }
+
+        impl $crate::ArchiveUnsized for dyn $Trait {
+            type Archived = $crate::std_impl::shared::registry::ArchivedDyn<dyn $Trait>;
+            type MetadataResolver = ();
+
+            #[inline]
+            fn resolve_unsized(
+                &self,
+                pos: usize,
+                target_pos: usize,
+                _resolver: (),
+                out: &mut core::mem::MaybeUninit<$crate::RelPtr<Self::Archived>>,
+            ) {
+                $crate::RelPtr::resolve(pos, target_pos, out);
+            }
+        }
+
+        impl<S> $crate::SerializeUnsized<S> for dyn $Trait
+        where
+            S: $crate::std_impl::shared::registry::ProvidesSerializeRegistry<dyn $Trait> + ?Sized,
+        {
+            #[inline]
+            fn serialize_unsized(&self, serializer: &mut S) -> Result<usize, S::Error> {
+                $crate::std_impl::shared::registry::serialize_dyn_unsized(self, serializer)
+            }
+
+            #[inline]
+            fn serialize_metadata(&self, _: &mut S) -> Result<Self::MetadataResolver, S::Error> {
+                Ok(())
+            }
+        }
+    };
+}
+
+#[cfg(all(test, feature = "validation"))]
+mod tests {
+    use core::convert::Infallible;
+
+    use super::*;
+
+    trait Component: Any {
+        fn value(&self) -> u32;
+    }
+
+    crate::archive_dyn!(Component);
+
+    #[repr(transparent)]
+    struct Widget(u32);
+
+    impl DynTypeTag for Widget {
+        const TYPE_TAG: TypeTag = 1;
+    }
+
+    impl Component for Widget {
+        fn value(&self) -> u32 {
+            self.0
+        }
+    }
+
+    impl Archive for Widget {
+        type Archived = Widget;
+        type Resolver = ();
+
+        fn resolve(&self, _pos: usize, _resolver: (), out: &mut MaybeUninit<Self::Archived>) {
+            unsafe { out.as_mut_ptr().write(Widget(self.0)) };
+        }
+    }
+
+    impl crate::ArchiveUnsized for Widget {
+        type Archived = Widget;
+        type MetadataResolver = ();
+
+        fn resolve_unsized(
+            &self,
+            pos: usize,
+            target_pos: usize,
+            _resolver: (),
+            out: &mut MaybeUninit<RelPtr<Self::Archived>>,
+        ) {
+            RelPtr::resolve(pos, target_pos, out);
+        }
+    }
+
+    impl<S: SharedSerializer + ?Sized> SerializeUnsized<S> for Widget {
+        fn serialize_unsized(&self, serializer: &mut S) -> Result<usize, S::Error> {
+            let pos = serializer.pos();
+            serializer.write(&self.0.to_ne_bytes())?;
+            Ok(pos)
+        }
+
+        fn serialize_metadata(&self, _: &mut S) -> Result<(), S::Error> {
+            Ok(())
+        }
+    }
+
+    impl<D: SharedDeserializer + ?Sized> DeserializeUnsized<Widget, D> for Widget {
+        fn deserialize_unsized(
+            &self,
+            _deserializer: &mut D,
+            alloc: impl FnOnce(usize) -> *mut u8,
+        ) -> Result<*mut (), D::Error> {
+            let ptr = alloc(core::mem::size_of::<Widget>()).cast::<Widget>();
+            unsafe { ptr.write(Widget(self.0)) };
+            Ok(ptr.cast())
+        }
+
+        fn deserialize_metadata(&self, _deserializer: &mut D) -> Result<(), D::Error> {
+            Ok(())
+        }
+    }
+
+    // `Widget`'s archived form is itself, so there's nothing to recursively check beyond the
+    // bytes already having been bounds-checked by `ArchivedDyn::check_bytes` before this runs.
+    unsafe impl CheckBytes<FakeContext> for Widget {
+        type Error = Infallible;
+
+        unsafe fn check_bytes<'a>(value: *const Self, _context: &mut FakeContext) -> Result<&'a Self, Self::Error> {
+            Ok(&*value)
+        }
+    }
+
+    /// A bare-bones [`SharedSerializer`] that just appends to an in-memory buffer, with its own
+    /// [`SerializeRegistry`] for `dyn Component` wired up front.
+    struct FakeSerializer {
+        bytes: Vec<u8>,
+        registry: rc::Rc<SerializeRegistry<Self>>,
+    }
+
+    impl SharedSerializer for FakeSerializer {
+        type Error = Infallible;
+
+        fn pos(&self) -> usize {
+            self.bytes.len()
+        }
+
+        fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+            self.bytes.extend_from_slice(bytes);
+            Ok(())
+        }
+
+        fn serialize_shared<T: SerializeUnsized<Self> + ?Sized>(
+            &mut self,
+            value: &T,
+        ) -> Result<usize, Self::Error> {
+            value.serialize_unsized(self)
+        }
+    }
+
+    impl ProvidesSerializeRegistry<dyn Component> for FakeSerializer {
+        fn serialize_registry(&self) -> rc::Rc<SerializeRegistry<Self>> {
+            self.registry.clone()
+        }
+    }
+
+    /// A bare-bones [`SharedDeserializer`] with no dedup of its own; this only exercises
+    /// `registry.rs`'s own serialize/check/deserialize wiring.
+    struct FakeDeserializer;
+
+    impl SharedDeserializer for FakeDeserializer {
+        type Error = Infallible;
+
+        fn deserialize_shared<T, P, F>(
+            &mut self,
+            archived: &T::Archived,
+            ctor: F,
+        ) -> Result<*mut T, Self::Error>
+        where
+            T: Archive,
+            T::Archived: DeserializeUnsized<T, Self>,
+            P: crate::de::SharedPointer,
+            F: FnOnce(*mut T) -> P,
+        {
+            let raw = archived
+                .deserialize_unsized(self, |size| unsafe {
+                    std::alloc::alloc(std::alloc::Layout::array::<u8>(size).unwrap())
+                })?
+                .cast::<T>();
+            // `ctor` may move the value into a new allocation (as `Rc::from(Box)` does), so hand
+            // back the shared pointer's own data address rather than `raw`.
+            let shared = ctor(raw);
+            let data = crate::de::SharedPointer::data_address(&shared) as *mut T;
+            forget(shared);
+            Ok(data)
+        }
+    }
+
+    /// A bare-bones [`ArchiveContext`] that trusts every relative pointer without bounds-checking
+    /// it; this only exercises dispatch through a [`TypeRegistry`].
+    struct FakeContext {
+        registry: rc::Rc<TypeRegistry<rc::Rc<dyn Component>, FakeDeserializer, Self>>,
+    }
+
+    impl ArchiveContext for FakeContext {
+        type Error = Infallible;
+
+        fn check_rel_ptr<T: ArchivePointee + ?Sized>(
+            &mut self,
+            rel_ptr: &RelPtr<T>,
+        ) -> Result<*const T, Self::Error> {
+            Ok(unsafe { rel_ptr.as_ptr() })
+        }
+    }
+
+    impl ProvidesTypeRegistry<dyn Component> for FakeContext {
+        type Strong = rc::Rc<dyn Component>;
+        type Deserializer = FakeDeserializer;
+
+        fn type_registry(&self) -> rc::Rc<TypeRegistry<Self::Strong, Self::Deserializer, Self>> {
+            self.registry.clone()
+        }
+    }
+
+    #[test]
+    fn archive_dyn_round_trips_through_serialize_check_and_deserialize() {
+        let mut serialize_registry = SerializeRegistry::new();
+        serialize_registry.register::<Widget>();
+
+        let mut type_registry = TypeRegistry::new();
+        type_registry.register::<Widget, rc::Rc<Widget>>(|widget| widget);
+        let type_registry = rc::Rc::new(type_registry);
+
+        let mut serializer = FakeSerializer {
+            bytes: Vec::new(),
+            registry: rc::Rc::new(serialize_registry),
+        };
+
+        let component: rc::Rc<dyn Component> = rc::Rc::new(Widget(42));
+        let pos = component.as_ref().serialize_unsized(&mut serializer).unwrap();
+
+        let archived = unsafe {
+            &*serializer.bytes.as_ptr().add(pos).cast::<ArchivedDyn<dyn Component>>()
+        };
+
+        archived.check_bytes(&type_registry, &mut FakeContext {
+            registry: type_registry.clone(),
+        }).expect("a freshly serialized ArchivedDyn should validate");
+
+        let mut deserializer = FakeDeserializer;
+        let deserialized = unsafe { archived.deserialize(&type_registry, &mut deserializer) }
+            .expect("a validated ArchivedDyn should deserialize");
+
+        assert_eq!(deserialized.value(), 42);
+    }
+}
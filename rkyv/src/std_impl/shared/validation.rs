@@ -0,0 +1,303 @@
+//! Validation implementations for shared pointers.
+
+use core::{any::TypeId, fmt};
+use std::{collections::HashMap, error::Error};
+
+use bytecheck::CheckBytes;
+
+use crate::{validation::{ArchiveContext, SharedContext}, ArchivePointee, RelPtr};
+
+use super::{
+    ArchivedArc, ArchivedArcWeak, ArchivedArcWeakTag, ArchivedArcWeakVariantSome, ArchivedRc,
+    ArchivedRcWeak, ArchivedRcWeakTag, ArchivedRcWeakVariantSome,
+};
+
+/// Errors that can occur when checking an archived shared pointer (`ArchivedRc`/`ArchivedArc`).
+#[derive(Debug)]
+pub enum SharedPointerError<T, C> {
+    /// The relative pointer failed its bounds check against the archive buffer.
+    PointerCheckFailed(C),
+    /// The pointee failed validation.
+    PointeeCheckFailed(T),
+}
+
+impl<T: fmt::Display, C: fmt::Display> fmt::Display for SharedPointerError<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PointerCheckFailed(e) => write!(f, "shared pointer failed bounds check: {}", e),
+            Self::PointeeCheckFailed(e) => write!(f, "shared pointee failed validation: {}", e),
+        }
+    }
+}
+
+impl<T: Error + 'static, C: Error + 'static> Error for SharedPointerError<T, C> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::PointerCheckFailed(e) => Some(e),
+            Self::PointeeCheckFailed(e) => Some(e),
+        }
+    }
+}
+
+/// Errors that can occur when checking an archived weak pointer (`ArchivedRcWeak`/
+/// `ArchivedArcWeak`).
+#[derive(Debug)]
+pub enum SharedWeakPointerError<T, C> {
+    /// The tag byte was neither `None` nor `Some`.
+    InvalidTag(u8),
+    /// The `Some` variant's shared pointer failed validation.
+    CheckFailed(SharedPointerError<T, C>),
+}
+
+impl<T: fmt::Display, C: fmt::Display> fmt::Display for SharedWeakPointerError<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTag(tag) => write!(f, "invalid archived weak pointer tag: {}", tag),
+            Self::CheckFailed(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<T: Error + 'static, C: Error + 'static> Error for SharedWeakPointerError<T, C> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidTag(_) => None,
+            Self::CheckFailed(e) => Some(e),
+        }
+    }
+}
+
+/// Checks a relative pointer shared by an `ArchivedRc`/`ArchivedArc`, deduplicating by target
+/// address so that a DAG of shared pointers is only ever validated once per target.
+unsafe fn check_shared_rel_ptr<T, C>(
+    rel_ptr: &RelPtr<T>,
+    context: &mut C,
+) -> Result<(), SharedPointerError<T::Error, C::Error>>
+where
+    T: ArchivePointee + CheckBytes<C> + ?Sized + 'static,
+    C: ArchiveContext + SharedContext + ?Sized,
+{
+    // Bounds-checks the whole pointee range `[target, target + size_of_val(pointee))` against
+    // the archive buffer before we dereference anything.
+    let ptr = context
+        .check_rel_ptr(rel_ptr)
+        .map_err(SharedPointerError::PointerCheckFailed)?;
+
+    // Many `ArchivedRc`/`ArchivedArc` values may share the same target (a DAG, not a tree), so
+    // only the pointer that discovers a target first actually descends into it; later pointers
+    // to the same (address, type) short-circuit instead of re-validating the same subobject.
+    let not_yet_checked = context
+        .register_shared_ptr(ptr as *const u8 as *const (), TypeId::of::<T>())
+        .map_err(SharedPointerError::PointerCheckFailed)?;
+    if not_yet_checked {
+        T::check_bytes(ptr.cast(), context).map_err(SharedPointerError::PointeeCheckFailed)?;
+    }
+
+    Ok(())
+}
+
+unsafe impl<T, C> CheckBytes<C> for ArchivedRc<T>
+where
+    T: ArchivePointee + CheckBytes<C> + ?Sized + 'static,
+    C: ArchiveContext + SharedContext + ?Sized,
+{
+    type Error = SharedPointerError<T::Error, C::Error>;
+
+    unsafe fn check_bytes<'a>(value: *const Self, context: &mut C) -> Result<&'a Self, Self::Error> {
+        check_shared_rel_ptr(&(*value).0, context)?;
+        Ok(&*value)
+    }
+}
+
+unsafe impl<T, C> CheckBytes<C> for ArchivedArc<T>
+where
+    T: ArchivePointee + CheckBytes<C> + ?Sized + 'static,
+    C: ArchiveContext + SharedContext + ?Sized,
+{
+    type Error = SharedPointerError<T::Error, C::Error>;
+
+    unsafe fn check_bytes<'a>(value: *const Self, context: &mut C) -> Result<&'a Self, Self::Error> {
+        check_shared_rel_ptr(&(*value).0, context)?;
+        Ok(&*value)
+    }
+}
+
+unsafe impl<T, C> CheckBytes<C> for ArchivedRcWeak<T>
+where
+    T: ArchivePointee + CheckBytes<C> + ?Sized + 'static,
+    C: ArchiveContext + SharedContext + ?Sized,
+{
+    type Error = SharedWeakPointerError<T::Error, C::Error>;
+
+    unsafe fn check_bytes<'a>(value: *const Self, context: &mut C) -> Result<&'a Self, Self::Error> {
+        match *value.cast::<u8>() {
+            tag if tag == ArchivedRcWeakTag::None as u8 => Ok(&*value),
+            tag if tag == ArchivedRcWeakTag::Some as u8 => {
+                let variant = value.cast::<ArchivedRcWeakVariantSome<T>>();
+                check_shared_rel_ptr(&((*variant).1).0, context)
+                    .map_err(SharedWeakPointerError::CheckFailed)?;
+                Ok(&*value)
+            }
+            tag => Err(SharedWeakPointerError::InvalidTag(tag)),
+        }
+    }
+}
+
+unsafe impl<T, C> CheckBytes<C> for ArchivedArcWeak<T>
+where
+    T: ArchivePointee + CheckBytes<C> + ?Sized + 'static,
+    C: ArchiveContext + SharedContext + ?Sized,
+{
+    type Error = SharedWeakPointerError<T::Error, C::Error>;
+
+    unsafe fn check_bytes<'a>(value: *const Self, context: &mut C) -> Result<&'a Self, Self::Error> {
+        match *value.cast::<u8>() {
+            tag if tag == ArchivedArcWeakTag::None as u8 => Ok(&*value),
+            tag if tag == ArchivedArcWeakTag::Some as u8 => {
+                let variant = value.cast::<ArchivedArcWeakVariantSome<T>>();
+                check_shared_rel_ptr(&((*variant).1).0, context)
+                    .map_err(SharedWeakPointerError::CheckFailed)?;
+                Ok(&*value)
+            }
+            tag => Err(SharedWeakPointerError::InvalidTag(tag)),
+        }
+    }
+}
+
+/// A reference-count-by-target map built while validating an archive.
+///
+/// [`SharedContext::register_shared_ptr`] only reports whether a target has been *seen* before,
+/// which is enough to avoid re-validating it. Implementors of this trait additionally track how
+/// many `ArchivedRc`/`ArchivedArc` pointers resolve to each target, which is what lets
+/// [`ArchivedRc::get_mut`](super::ArchivedRc::get_mut) tell a uniquely-owned target apart from
+/// one with other live pointers into it.
+pub trait SharedPointerCounts {
+    /// Returns the number of archived shared pointers that target `ptr`, as observed during
+    /// validation.
+    fn shared_count(&self, ptr: *const ()) -> usize;
+}
+
+/// A [`SharedContext`] (and [`ArchiveContext`]) decorator that tallies how many times each
+/// shared-pointer target is visited while validating an archive.
+///
+/// `register_shared_ptr` only reports whether a target has been seen *before*, so the dedup path
+/// in [`check_shared_rel_ptr`] only descends into a target's bytes once per archive, exactly as
+/// it should for a DAG. But that same short-circuiting throws away the count `get_mut` needs:
+/// counting here happens on every call this decorator forwards, whether or not the inner context
+/// goes on to skip revalidation, so the tally reflects every `ArchivedRc`/`ArchivedArc` that
+/// resolves to a given target, not just the first one discovered.
+///
+/// Wrap the context passed to `check_bytes` in one of these, then hand the finished map to
+/// `get_mut` via [`counts`](Self::counts):
+///
+/// ```ignore
+/// let mut context = SharedPointerCountMap::new(context);
+/// ArchivedExample::check_bytes(archive.as_ptr(), &mut context)?;
+/// let counts = context.counts();
+/// ArchivedRc::get_mut(example.example.as_mut(), counts);
+/// ```
+pub struct SharedPointerCountMap<C> {
+    inner: C,
+    counts: HashMap<*const (), usize>,
+}
+
+impl<C> SharedPointerCountMap<C> {
+    /// Wraps `inner`, starting with an empty count map.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Returns the count map accumulated so far.
+    ///
+    /// Most useful once validation of the whole archive has completed.
+    pub fn counts(&self) -> &impl SharedPointerCounts {
+        &self.counts
+    }
+
+    /// Unwraps this decorator, discarding the accumulated counts.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl SharedPointerCounts for HashMap<*const (), usize> {
+    fn shared_count(&self, ptr: *const ()) -> usize {
+        *self.get(&ptr).unwrap_or(&0)
+    }
+}
+
+impl<C: ArchiveContext> ArchiveContext for SharedPointerCountMap<C> {
+    type Error = C::Error;
+
+    fn check_rel_ptr<T: ArchivePointee + ?Sized>(
+        &mut self,
+        rel_ptr: &RelPtr<T>,
+    ) -> Result<*const T, Self::Error> {
+        self.inner.check_rel_ptr(rel_ptr)
+    }
+}
+
+impl<C: SharedContext> SharedContext for SharedPointerCountMap<C> {
+    type Error = C::Error;
+
+    fn register_shared_ptr(
+        &mut self,
+        ptr: *const (),
+        type_id: TypeId,
+    ) -> Result<bool, Self::Error> {
+        *self.counts.entry(ptr).or_insert(0) += 1;
+        self.inner.register_shared_ptr(ptr, type_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// A bare-bones `SharedContext` that only tracks which targets have been seen, the same as a
+    /// real validator's dedup table, without any of the surrounding bounds-checking machinery.
+    struct SeenTargets(HashSet<*const ()>);
+
+    impl SharedContext for SeenTargets {
+        type Error = Infallible;
+
+        fn register_shared_ptr(
+            &mut self,
+            ptr: *const (),
+            _type_id: TypeId,
+        ) -> Result<bool, Self::Error> {
+            Ok(self.0.insert(ptr))
+        }
+    }
+
+    // A diamond-shaped DAG (root -> {a, b} -> shared) is the minimal shape that exercises both
+    // properties `check_shared_rel_ptr` depends on: `shared` must be validated exactly once no
+    // matter how many parents point to it, and `get_mut` must still see every one of those
+    // parents reflected in its count. A cycle (shared pointing back into an ancestor) visits its
+    // target through this exact same `register_shared_ptr` call, so it's covered by the same
+    // repeated-registration behavior tested here, without needing a real self-referential
+    // archive buffer to demonstrate it.
+    #[test]
+    fn diamond_shaped_dag_dedups_while_still_counting_every_edge() {
+        let shared_target = 0x1000 as *const ();
+        let type_id = TypeId::of::<u8>();
+
+        let mut context = SharedPointerCountMap::new(SeenTargets(HashSet::new()));
+
+        // `a -> shared`: first time this target is seen, so it must be validated.
+        assert!(context.register_shared_ptr(shared_target, type_id).unwrap());
+        // `b -> shared`: same target, second edge in the DAG; validation must be skipped...
+        assert!(!context.register_shared_ptr(shared_target, type_id).unwrap());
+
+        // ...but the count must reflect both edges, not just the first.
+        assert_eq!(context.counts().shared_count(shared_target), 2);
+        // A target that was never registered has no recorded edges.
+        assert_eq!(context.counts().shared_count(0x2000 as *const ()), 0);
+    }
+}